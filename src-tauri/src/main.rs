@@ -6,24 +6,315 @@
 extern crate serde;
 
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use std::sync::Mutex;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{convert::TryFrom, error::Error};
-use tauri::{Manager, State};
+use tauri::{AppHandle, Manager, State};
 
+// How long the debounce thread keeps collecting events after the first
+// event of a batch before flushing every path in it to the frontend. Mirrors rust-analyzer's VFS watcher: we'd rather emit one
+// coalesced event per path than flood the UI during bulk operations.
+const WATCHER_DELAY: Duration = Duration::from_millis(250);
+
+// Identifies a registered root. Stable for the lifetime of the app run,
+// handed out by `Roots::add`.
+type RootId = u32;
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Create,
+    Write,
+    Remove,
+}
+
+impl ChangeKind {
+    fn from_event_kind(kind: &notify::EventKind) -> Option<ChangeKind> {
+        use notify::EventKind;
+        match kind {
+            EventKind::Create(_) => Some(ChangeKind::Create),
+            EventKind::Modify(_) => Some(ChangeKind::Write),
+            EventKind::Remove(_) => Some(ChangeKind::Remove),
+            _ => None,
+        }
+    }
+
+    // Whether the path `kind` describes is a directory, straight from the
+    // backend (inotify/FSEvents/ReadDirectoryChangesW all report this on the
+    // raw event). `None` when the event itself doesn't say, e.g. `Modify`,
+    // where the path still exists and can just be `stat`'d.
+    //
+    // This matters for `Remove`: by the time we'd otherwise get to check,
+    // the path is already gone, so `path.is_dir()` always reports `false`
+    // and a directory's own removal event could never match a
+    // directory-only ignore pattern (a trailing-`/` pattern like `build/`).
+    fn is_dir_hint(kind: &notify::EventKind) -> Option<bool> {
+        use notify::event::{CreateKind, RemoveKind};
+        use notify::EventKind;
+        match kind {
+            EventKind::Create(CreateKind::Folder) => Some(true),
+            EventKind::Create(CreateKind::File) => Some(false),
+            EventKind::Remove(RemoveKind::Folder) => Some(true),
+            EventKind::Remove(RemoveKind::File) => Some(false),
+            _ => None,
+        }
+    }
+
+    // Collapse a pending change with a newly observed one. The invariant we
+    // preserve: once the debounce window is quiet, the sum of emitted
+    // events must equal the true filesystem state, so a Create immediately
+    // undone by a Remove should vanish rather than round-trip to the
+    // frontend.
+    fn merge(existing: Option<ChangeKind>, incoming: ChangeKind) -> Option<ChangeKind> {
+        match (existing, incoming) {
+            (None, kind) => Some(kind),
+            (Some(ChangeKind::Create), ChangeKind::Remove) => None,
+            (Some(ChangeKind::Create), ChangeKind::Write) => Some(ChangeKind::Create),
+            (Some(ChangeKind::Remove), ChangeKind::Create) => Some(ChangeKind::Write),
+            (Some(_), ChangeKind::Remove) => Some(ChangeKind::Remove),
+            (Some(_), newer) => Some(newer),
+        }
+    }
+}
+
+// A change still waiting out the debounce window for a single path, plus
+// whatever we've learned about that path along the way.
+#[derive(Clone, Copy)]
+struct PendingChange {
+    kind: ChangeKind,
+    // Best known answer to "is this path a directory?", carried forward
+    // from whichever raw event last told us (see `ChangeKind::is_dir_hint`).
+    // Still `None` if no event merged into this entry carried a hint (a
+    // `Modify`, or a create/remove of unspecified kind); the flush then
+    // falls back to a plain `stat`, which reports "not a directory" for a
+    // path that no longer exists.
+    is_dir: Option<bool>,
+}
+
+// Spawns the dedicated debouncing thread that owns the notify receiver.
+// Raw events, tagged with the root they came from, are coalesced by
+// `(root_id, path)` for `WATCHER_DELAY` after the first event of a batch and
+// then flushed to the frontend as a `TaskResult::SingleFile` carrying the
+// file's current content; events arriving after that start the next batch.
+// Ignore matching happens once per flushed path here,
+// not once per raw event in `apply_event`, and only ever under the `roots`
+// lock long enough to clone out the handful of root paths it needs —
+// never across the ignore-file reads or the `fs::read_to_string` below,
+// so a burst of watcher events can't stall `list_dir_files`, `open_file`,
+// `save_file` and the other commands that share the same lock.
+fn spawn_debounce_thread(
+    rx: mpsc::Receiver<(RootId, notify::Event)>,
+    handle: AppHandle,
+    roots: Arc<Mutex<RootsInner>>,
+) {
+    thread::spawn(move || loop {
+        let (first_root, first_event) = match rx.recv() {
+            Ok(tagged) => tagged,
+            Err(_) => break, // sender dropped, app is shutting down
+        };
+
+        let mut pending: HashMap<(RootId, PathBuf), PendingChange> = HashMap::new();
+        apply_event(&mut pending, first_root, first_event);
+
+        let deadline = Instant::now() + WATCHER_DELAY;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            match rx.recv_timeout(remaining) {
+                Ok((root_id, event)) => apply_event(&mut pending, root_id, event),
+                Err(_) => break,
+            }
+        }
+
+        let root_ids: std::collections::HashSet<RootId> =
+            pending.keys().map(|(root_id, _)| *root_id).collect();
+        let root_paths: HashMap<RootId, PathBuf> = {
+            let inner = roots.lock().unwrap();
+            root_ids
+                .into_iter()
+                .filter_map(|root_id| {
+                    inner
+                        .entries
+                        .get(&root_id)
+                        .map(|entry| (root_id, entry.config.path.clone()))
+                })
+                .collect()
+        };
+
+        for ((root_id, path), change) in pending {
+            let Some(root_path) = root_paths.get(&root_id) else {
+                continue; // root was removed while its events were in flight
+            };
+
+            let is_dir = change.is_dir.unwrap_or_else(|| path.is_dir());
+            if is_ignored(root_path, &path, is_dir) {
+                continue;
+            }
+
+            let relative_path = path
+                .strip_prefix(root_path)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+
+            // Read the file's current content right here on the debounce
+            // thread (same read `open_file` does) so the frontend gets the
+            // post-debounce state directly and never has to round-trip a
+            // follow-up read for every change. A deletion carries no text.
+            let text = match change.kind {
+                ChangeKind::Remove => None,
+                ChangeKind::Create | ChangeKind::Write => fs::read_to_string(&path).ok(),
+            };
+
+            let result = TaskResult::SingleFile {
+                root_id,
+                relative_path,
+                kind: Some(change.kind),
+                text,
+            };
+            if let Err(e) = handle.emit_all("file_changed", result) {
+                eprintln!("failed to emit file_changed: {:?}", e);
+            }
+        }
+    });
+}
+
+// Merges one raw notify event into `pending`. Deliberately doesn't touch
+// `roots` or the filesystem at all — ignore matching is deferred to flush
+// time in `spawn_debounce_thread`, once per deduplicated path instead of
+// once per raw event, so a root being watched recursively doesn't mean
+// re-parsing its ignore files for every single change notify hands us.
+fn apply_event(
+    pending: &mut HashMap<(RootId, PathBuf), PendingChange>,
+    root_id: RootId,
+    event: notify::Event,
+) {
+    let Some(kind) = ChangeKind::from_event_kind(&event.kind) else {
+        return;
+    };
+    let is_dir = ChangeKind::is_dir_hint(&event.kind);
+
+    for path in event.paths {
+        let key = (root_id, path);
+        let existing = pending.get(&key);
+        let merged_is_dir = is_dir.or_else(|| existing.and_then(|change| change.is_dir));
+        match ChangeKind::merge(existing.map(|change| change.kind), kind) {
+            Some(merged) => {
+                pending.insert(
+                    key,
+                    PendingChange {
+                        kind: merged,
+                        is_dir: merged_is_dir,
+                    },
+                );
+            }
+            None => {
+                pending.remove(&key);
+            }
+        }
+    }
+}
+
+// Checks `path` (rooted under `root`), and each of its parent directories
+// below `root`, against the `.gitignore`/`.ignore` files collected by
+// `build_ignore_matcher`, so a path inside an ignored directory is ignored
+// too, just as the walkers never descend into such a directory. Built
+// fresh on every call rather than cached, so a nested or just-edited
+// ignore file is picked up immediately instead of only at `add_root` time
+// — giving the watcher the same hierarchical behavior `ignore::WalkBuilder`
+// already gives `list_dir_files`/`walk_dir_files`/`list_path`. `is_dir`
+// must come from the caller rather than a fresh `path.is_dir()`: for a
+// `Remove` event the path is already gone by the time we get here, so
+// re-`stat`-ing it would always say "not a directory" and directory-only
+// ignore patterns (a trailing `/`) would never match a directory's own
+// removal.
+fn is_ignored(root: &Path, path: &Path, is_dir: bool) -> bool {
+    let matcher = build_ignore_matcher(root, path);
+    path.ancestors()
+        .take_while(|p| *p != root && p.starts_with(root))
+        .enumerate()
+        .any(|(depth, p)| matcher.matched(p, depth > 0 || is_dir).is_ignore())
+}
+
+// Builds a gitignore matcher from every `.gitignore`/`.ignore` file found
+// from the filesystem root down to `path`'s parent directory — both above
+// and below `root`. Patterns follow standard gitignore semantics (the
+// `ignore` crate already implements `!` negation, trailing-`/`
+// directory-only patterns, and leading-`/` anchoring, with the last
+// matching pattern winning). Looking above `root` too mirrors
+// `ignore::WalkBuilder::parents(true)`, which is what `list_dir_files`/
+// `walk_dir_files`/`list_path` already use: a root nested inside a bigger
+// gitignored tree should be filtered the same way whether the frontend is
+// listing it or the watcher is reporting a change in it.
+fn build_ignore_matcher(root: &Path, path: &Path) -> ignore::gitignore::Gitignore {
+    let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+    for dir in ancestor_dirs_above(root).into_iter().chain(ignore_dirs_between(root, path)) {
+        for name in [".gitignore", ".ignore"] {
+            let candidate = dir.join(name);
+            if candidate.is_file() {
+                builder.add(candidate);
+            }
+        }
+    }
+    builder
+        .build()
+        .unwrap_or_else(|_| ignore::gitignore::Gitignore::empty())
+}
+
+// Every ancestor of `root`, from the filesystem root down to (but not
+// including) `root` itself. Ordered topmost-first so nearer directories
+// are added to the builder later, matching gitignore's "closer file wins"
+// precedence.
+fn ancestor_dirs_above(root: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = root.ancestors().skip(1).map(Path::to_path_buf).collect();
+    dirs.reverse();
+    dirs
+}
+
+// Every directory from `root` down to (but not including) `path`'s own
+// final component, in descending order. These are the directories whose
+// ignore files can affect whether `path` is ignored.
+fn ignore_dirs_between(root: &Path, path: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+
+    let Ok(relative) = path.strip_prefix(root) else {
+        return dirs;
+    };
+    let mut components = relative.components();
+    components.next_back(); // drop path's own final component
+
+    let mut current = root.to_path_buf();
+    for component in components {
+        current.push(component.as_os_str());
+        dirs.push(current.clone());
+    }
+    dirs
+}
+
+// A file as exposed to the frontend: always relative to whichever root it
+// lives under, never a raw absolute OS path.
 #[derive(serde::Serialize)]
 struct File {
+    root_id: RootId,
+    relative_path: String,
     name: String,
-    path: String,
     content: Option<String>,
     preview: Option<String>,
 }
 
 #[derive(serde::Serialize)]
 struct Directory {
+    root_id: RootId,
+    relative_path: String,
     name: String,
-    path: String,
     children_count: i32,
 }
 
@@ -33,76 +324,118 @@ enum FsElement {
     Directory(Directory),
 }
 
-//This function reads the contents of the directory, and for each file or
-//directory in the directory, it returns an FsElement enum variant representing
-//the file or directory.
+// A single-level walker over `dir` that respects `.gitignore`/`.ignore`
+// files (including ones in parent directories) and the caller's
+// hidden-dotfile preference.
+fn single_level_walker(dir: &Path, show_hidden: bool) -> ignore::Walk {
+    ignore::WalkBuilder::new(dir)
+        .hidden(!show_hidden)
+        .git_ignore(true)
+        .ignore(true)
+        .parents(true)
+        .max_depth(Some(1))
+        .build()
+}
+
+//This function reads the contents of a directory under a registered root,
+//and for each file or directory in it, returns an FsElement enum variant
+//representing the file or directory. Entries matched by
+//`.gitignore`/`.ignore` are skipped, and dotfiles are hidden unless
+//`show_hidden` is set.
 #[tauri::command]
-async fn list_dir_files(path: String) -> Vec<FsElement> {
-    let paths = fs::read_dir(path).unwrap();
-    let files: Vec<FsElement> = paths
-        .map(|e| e.unwrap())
-        .filter(|p| !p.file_name().to_str().unwrap().starts_with("."))
-        .map(|this_path| -> Result<FsElement, Box<dyn Error>> {
-            if this_path.metadata()?.is_dir() {
+async fn list_dir_files(
+    root_id: RootId,
+    relative_path: Option<String>,
+    show_hidden: Option<bool>,
+    roots: State<'_, Roots>,
+) -> Result<Vec<FsElement>, String> {
+    let show_hidden = show_hidden.unwrap_or(false);
+    let (root_path, target) = {
+        let inner = roots.inner.lock().unwrap();
+        let entry = inner
+            .entries
+            .get(&root_id)
+            .ok_or_else(|| format!("no root with id {root_id}"))?;
+        let target = match &relative_path {
+            Some(rel) => entry.config.path.join(rel),
+            None => entry.config.path.clone(),
+        };
+        (entry.config.path.clone(), target)
+    };
+
+    let elements = single_level_walker(&target, show_hidden)
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.path() != target)
+        .map(|entry| -> Result<FsElement, Box<dyn Error>> {
+            let this_path = entry.path();
+            let relative_path = this_path
+                .strip_prefix(&root_path)?
+                .to_string_lossy()
+                .to_string();
+
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
                 let children_count = i32::try_from(
-                    fs::read_dir(this_path.path())?
-                        .filter(|p| {
-                            !p.as_ref()
-                                .unwrap()
-                                .file_name()
-                                .to_str()
-                                .unwrap()
-                                .starts_with('.')
-                        })
+                    single_level_walker(this_path, show_hidden)
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.path() != this_path)
                         .count(),
                 )?;
 
                 Ok(FsElement::Directory(Directory {
-                    name: this_path.file_name().to_str().unwrap().to_string(),
-                    path: this_path.path().to_str().unwrap().to_string(),
+                    root_id,
+                    relative_path,
+                    name: entry.file_name().to_string_lossy().to_string(),
                     children_count,
                 }))
             } else {
-                let name = this_path.file_name().to_str().expect("error").to_string();
-                let file_path = this_path.path().to_str().expect("error").to_string();
-
-                let content = match fs::read_to_string(&file_path) {
-                    Ok(content) => content,
-                    Err(_) => String::from(""),
-                };
+                let name = entry.file_name().to_string_lossy().to_string();
+                let content = fs::read_to_string(this_path).unwrap_or_default();
 
-                return Ok(FsElement::File(File {
+                Ok(FsElement::File(File {
+                    root_id,
+                    relative_path,
                     name,
-                    path: file_path,
                     content: None,
                     preview: Some(content.chars().take(100).collect()),
-                }));
+                }))
             }
         })
-        .map(|res| res.unwrap())
+        .filter_map(|res| res.ok())
         .collect();
-    files
-}
-
-//Lists all paths recursively, and returns a vector containing them
-//If deep == false, only lists files in the current folder
-fn list_path(path: String, deep: bool) -> Vec<String> {
-    let paths = fs::read_dir(path).unwrap();
-    let mut all_path = vec![];
-    paths.map(|e| e.unwrap()).for_each(|p| {
-        let path_string = p.path().to_str().unwrap().to_string();
-        if p.metadata().unwrap().is_dir() && deep {
-            all_path.extend(list_path(path_string, deep))
-        } else {
-            all_path.push(path_string);
-        }
-    });
-    all_path
+
+    Ok(elements)
+}
+
+//Lists all paths recursively under an arbitrary filesystem location, and
+//returns a vector containing them. If deep == false, only lists the entries
+//of the current folder. Entries matched by `.gitignore`/`.ignore` are
+//skipped, and dotfiles are hidden unless `show_hidden` is set. Unlike
+//`list_dir_files`, this isn't scoped to a registered root: the frontend
+//uses it to browse the filesystem (e.g. picking a folder) before calling
+//`add_root`.
+fn list_path(path: String, deep: bool, show_hidden: bool) -> Vec<String> {
+    let mut builder = ignore::WalkBuilder::new(&path);
+    builder
+        .hidden(!show_hidden)
+        .git_ignore(true)
+        .ignore(true)
+        .parents(true);
+    if !deep {
+        builder.max_depth(Some(1));
+    }
+
+    let root = Path::new(&path);
+    builder
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.path() != root)
+        .map(|entry| entry.path().to_string_lossy().to_string())
+        .collect()
 }
 
 #[tauri::command]
-fn list_path_deep(path: String, deep: bool) -> Vec<String> {
-    list_path(path, deep)
+fn list_path_deep(path: String, deep: bool, show_hidden: Option<bool>) -> Vec<String> {
+    list_path(path, deep, show_hidden.unwrap_or(false))
 }
 
 #[tauri::command]
@@ -116,106 +449,579 @@ fn is_dir(path: String) -> bool {
     }
 }
 
-#[tauri::command]
-async fn open_file(path: String) -> File {
-    return |path| -> Result<File, Box<dyn Error>> {
-        let content = fs::read_to_string(&path)?;
-
-        Ok(File {
-            name: String::from(&path),
-            path,
-            content: Some(content),
-            preview: None,
+// Work handed off to the background indexer thread so the main thread
+// never blocks on directory walks or file reads.
+enum Task {
+    BulkLoadDir {
+        root_id: RootId,
+        root_path: PathBuf,
+        show_hidden: bool,
+    },
+    RefreshFile {
+        root_id: RootId,
+        relative_path: String,
+        abs_path: PathBuf,
+    },
+}
+
+// Results streamed back to the frontend as each piece of work finishes.
+#[derive(serde::Serialize, Debug)]
+#[serde(tag = "type")]
+enum TaskResult {
+    BulkLoadRoot {
+        root_id: RootId,
+        files: Vec<File>,
+    },
+    SingleFile {
+        root_id: RootId,
+        relative_path: String,
+        // `None` for a manual `refresh_file` (there's no create/write/remove
+        // to report, just "here's the current state"); `Some` whenever this
+        // result came from the watcher, so the frontend can tell creates,
+        // writes and removes apart instead of inferring it from `text`.
+        kind: Option<ChangeKind>,
+        text: Option<String>,
+    },
+}
+
+struct Indexer(crossbeam_channel::Sender<Task>);
+
+// Spawns the background worker thread, modeled on rust-analyzer's
+// `thread_worker`: it owns the receiving end of the task channel and
+// emits a `TaskResult` for every task as soon as that task completes, so
+// one task's result never waits on the tasks queued behind it. A
+// `BulkLoadDir` walks its whole tree before emitting its single
+// `BulkLoadRoot` result.
+fn spawn_indexer(handle: AppHandle) -> crossbeam_channel::Sender<Task> {
+    let (tx, rx) = crossbeam_channel::unbounded::<Task>();
+
+    thread::spawn(move || {
+        for task in rx {
+            let result = match task {
+                Task::BulkLoadDir {
+                    root_id,
+                    root_path,
+                    show_hidden,
+                } => TaskResult::BulkLoadRoot {
+                    root_id,
+                    files: walk_dir_files(root_id, &root_path, show_hidden),
+                },
+                Task::RefreshFile {
+                    root_id,
+                    relative_path,
+                    abs_path,
+                } => TaskResult::SingleFile {
+                    root_id,
+                    relative_path,
+                    kind: None,
+                    text: fs::read_to_string(abs_path).ok(),
+                },
+            };
+            if let Err(e) = handle.emit_all("index_result", result) {
+                eprintln!("failed to emit index_result: {:?}", e);
+            }
+        }
+    });
+
+    tx
+}
+
+// Walks `root_path` off the main thread, reading every file it finds.
+// Respects `.gitignore`/`.ignore` files the same way `list_dir_files`
+// does, and hides dotfiles unless `show_hidden` is set.
+fn walk_dir_files(root_id: RootId, root_path: &Path, show_hidden: bool) -> Vec<File> {
+    ignore::WalkBuilder::new(root_path)
+        .hidden(!show_hidden)
+        .git_ignore(true)
+        .ignore(true)
+        .parents(true)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter_map(|entry| {
+            let this_path = entry.path();
+            let relative_path = this_path.strip_prefix(root_path).ok()?.to_string_lossy().to_string();
+            let name = entry.file_name().to_str().unwrap_or_default().to_string();
+            let content = fs::read_to_string(this_path).unwrap_or_default();
+
+            Some(File {
+                root_id,
+                relative_path,
+                name,
+                content: None,
+                preview: Some(content.chars().take(100).collect()),
+            })
         })
-    }(path)
-    .unwrap();
+        .collect()
 }
-struct Watch(Mutex<RecommendedWatcher>);
 
-//Set watcher for given directory
+// Kicks off a deep scan of a registered root on the background indexer
+// and returns immediately; the root's files arrive together in one
+// `index_result` event once the walk finishes.
 #[tauri::command]
-async fn watch(path: String, watcher: State<'_, Watch>) -> Result<(), ()> {
-    println!("Watching {}", &path);
-
-    let res_path = check_path(path);
+fn index_dir(
+    root_id: RootId,
+    show_hidden: Option<bool>,
+    roots: State<'_, Roots>,
+    indexer: State<'_, Indexer>,
+) -> Result<(), String> {
+    let root_path = {
+        let inner = roots.inner.lock().unwrap();
+        inner
+            .entries
+            .get(&root_id)
+            .map(|entry| entry.config.path.clone())
+            .ok_or_else(|| format!("no root with id {root_id}"))?
+    };
 
-    watcher
+    indexer
         .0
-        .lock()
-        .unwrap()
-        .watch(Path::new(&res_path), RecursiveMode::Recursive)
-        .unwrap();
-
-    Ok(())
+        .send(Task::BulkLoadDir {
+            root_id,
+            root_path,
+            show_hidden: show_hidden.unwrap_or(false),
+        })
+        .map_err(|e| e.to_string())
 }
-//Stop watcher for given directory
+
+// Re-reads a single file on the background indexer and returns
+// immediately; the refreshed content arrives via an `index_result` event.
+// Useful after a listing-affecting setting (hidden-file toggle, ignore
+// rule) changes and a previously-skipped file needs to be pulled in.
 #[tauri::command]
-async fn unwatch(path: String, watcher: State<'_, Watch>) -> Result<(), ()> {
-    println!("Stop watching {}", &path);
-    let res_path = check_path(path);
+fn refresh_file(
+    root_id: RootId,
+    relative_path: String,
+    roots: State<'_, Roots>,
+    indexer: State<'_, Indexer>,
+) -> Result<(), String> {
+    let abs_path = roots.resolve(root_id, &relative_path)?;
 
-    watcher
+    indexer
         .0
-        .lock()
-        .unwrap()
-        .unwatch(Path::new(&res_path))
-        .unwrap();
+        .send(Task::RefreshFile {
+            root_id,
+            relative_path,
+            abs_path,
+        })
+        .map_err(|e| e.to_string())
+}
+
+// Opens a file addressed as `{root_id, relative_path}`, resolving it back
+// to an absolute path via the root registry.
+#[tauri::command]
+async fn open_file(
+    root_id: RootId,
+    relative_path: String,
+    roots: State<'_, Roots>,
+) -> Result<File, String> {
+    let abs_path = roots.resolve(root_id, &relative_path)?;
+    let content = fs::read_to_string(&abs_path).map_err(|e| e.to_string())?;
+    let name = abs_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
 
+    Ok(File {
+        root_id,
+        relative_path,
+        name,
+        content: Some(content),
+        preview: None,
+    })
+}
+
+#[derive(Debug, serde::Serialize)]
+struct SaveError {
+    message: String,
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError {
+            message: err.to_string(),
+        }
+    }
+}
+
+// Disambiguates concurrent `atomic_write` calls in this process that would
+// otherwise land on the same temp file name (e.g. a debounced autosave
+// racing a manual save of the same note).
+static SAVE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+// Writes `content` to `path` atomically: the full content lands in a
+// sibling temp file on the same filesystem and is fsync'd before a single
+// `rename` swaps it into place, so a crash mid-write can never leave a
+// half-written note behind. The temp file name is unique per call, so two
+// overlapping saves to the same `path` never share (and corrupt) the same
+// temp file.
+fn atomic_write(path: &Path, content: &str) -> std::io::Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let unique = SAVE_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let tmp_name = format!(
+        ".{}.{}.{}.tmp",
+        path.file_name().and_then(|n| n.to_str()).unwrap_or("save"),
+        std::process::id(),
+        unique
+    );
+    let tmp_path = parent.join(tmp_name);
+
+    {
+        let mut tmp_file = fs::File::create(&tmp_path)?;
+        tmp_file.write_all(content.as_bytes())?;
+        tmp_file.sync_all()?;
+    }
+
+    rename_into_place(&tmp_path, path)
+}
+
+#[cfg(not(windows))]
+fn rename_into_place(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to)
+}
+
+#[cfg(windows)]
+fn rename_into_place(from: &Path, to: &Path) -> std::io::Result<()> {
+    // `fs::rename` already maps to `MoveFileExW` with
+    // `MOVEFILE_REPLACE_EXISTING` on Windows, but a lingering handle on
+    // the destination can still make the first attempt fail, so clear it
+    // and retry once.
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) => {
+            let _ = fs::remove_file(to);
+            fs::rename(from, to)
+        }
+    }
+}
+
+// Persists edits to disk, addressed as `{root_id, relative_path}` and
+// resolved back to an absolute path via the root registry. Failures are
+// returned to the frontend as a `SaveError` instead of unwrapped, since a
+// failed save must surface to the user rather than crash the app.
+#[tauri::command]
+async fn save_file(
+    root_id: RootId,
+    relative_path: String,
+    content: String,
+    roots: State<'_, Roots>,
+) -> Result<(), SaveError> {
+    let abs_path = roots.resolve(root_id, &relative_path).map_err(|message| SaveError { message })?;
+    atomic_write(&abs_path, &content)?;
     Ok(())
 }
 
-// look up if path exists
-fn check_path(path: String) -> String {
-    println!("Checking {}", &path);
+// One registered vault/folder: its absolute location on disk and the
+// watcher that keeps it live. The watcher is kept alive purely by living
+// in this struct — dropping the entry stops watching the root. Ignore
+// rules aren't cached here: they're recomputed per path from `config.path`
+// so edits to `.gitignore`/`.ignore` files take effect immediately.
+struct RootEntry {
+    config: RootConfig,
+    _watcher: RecommendedWatcher,
+}
 
-    let path_loc = Path::new(path.as_str());
-    let mut path_buf = path_loc.to_path_buf();
+#[derive(Clone)]
+struct RootConfig {
+    path: PathBuf,
+}
+
+// What the frontend gets back after registering a root.
+#[derive(serde::Serialize, Clone)]
+struct RootInfo {
+    root_id: RootId,
+    name: String,
+}
+
+struct RootsInner {
+    next_id: RootId,
+    entries: HashMap<RootId, RootEntry>,
+}
+
+// The managed collection of watched roots, replacing the single
+// `Mutex<RecommendedWatcher>` the app used to carry. Each root owns its
+// own recursive watcher, keyed by `RootId`, so multiple vaults can be
+// open (and torn down) independently.
+#[derive(Clone)]
+struct Roots {
+    inner: Arc<Mutex<RootsInner>>,
+}
+
+impl Roots {
+    fn new() -> Self {
+        Roots {
+            inner: Arc::new(Mutex::new(RootsInner {
+                next_id: 1,
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    fn resolve(&self, root_id: RootId, relative_path: &str) -> Result<PathBuf, String> {
+        let inner = self.inner.lock().unwrap();
+        let entry = inner
+            .entries
+            .get(&root_id)
+            .ok_or_else(|| format!("no root with id {root_id}"))?;
+        Ok(entry.config.path.join(relative_path))
+    }
+}
+
+struct EventSender(Mutex<mpsc::Sender<(RootId, notify::Event)>>);
 
-    if !path_loc.exists() {
-        path_buf.pop();
-        check_path(path_buf.to_str().unwrap().to_string());
+// Registers a new root and starts a dedicated recursive watcher for it.
+// Fails if the path is already registered, so a root can never end up
+// watched twice.
+#[tauri::command]
+async fn add_root(
+    name: String,
+    path: String,
+    roots: State<'_, Roots>,
+    events: State<'_, EventSender>,
+) -> Result<RootInfo, String> {
+    let root_path = fs::canonicalize(&path).map_err(|e| e.to_string())?;
+
+    let mut inner = roots.inner.lock().unwrap();
+    if inner
+        .entries
+        .values()
+        .any(|entry| entry.config.path == root_path)
+    {
+        return Err(format!("{} is already watched", root_path.display()));
     }
-    return path_buf.to_str().unwrap().to_string();
+
+    let id = inner.next_id;
+    inner.next_id += 1;
+
+    let tx = events.0.lock().unwrap().clone();
+
+    let mut watcher =
+        notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| match res {
+            Ok(event) => {
+                if tx.send((id, event)).is_err() {
+                    eprintln!("debounce thread gone, dropping event for root {id}");
+                }
+            }
+            Err(e) => eprintln!("watch error: {:?}", e),
+        })
+        .map_err(|e| e.to_string())?;
+
+    watcher
+        .watch(&root_path, RecursiveMode::Recursive)
+        .map_err(|e| e.to_string())?;
+
+    let info = RootInfo {
+        root_id: id,
+        name,
+    };
+
+    inner.entries.insert(
+        id,
+        RootEntry {
+            config: RootConfig { path: root_path },
+            _watcher: watcher,
+        },
+    );
+
+    Ok(info)
+}
+
+// Unregisters a root and stops its watcher in one step: dropping the
+// `RootEntry` drops the `RecommendedWatcher` it owns.
+#[tauri::command]
+fn remove_root(root_id: RootId, roots: State<'_, Roots>) -> Result<(), String> {
+    let mut inner = roots.inner.lock().unwrap();
+    inner
+        .entries
+        .remove(&root_id)
+        .map(|_| ())
+        .ok_or_else(|| format!("no root with id {root_id}"))
 }
 
 //Main script
 fn main() -> notify::Result<()> {
     tauri::Builder::default()
         .setup(|app| {
-            // attach the notify watcher to the app
             let handle = app.handle();
 
-            //Setup watcher
-            let w =
-                notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
-                    match res {
-                        Ok(event) => {
-                            println!("{:?}", event);
-                            handle
-                                .emit_all(
-                                    "file_changed",
-                                    event.paths[0].to_str().unwrap().to_string(),
-                                )
-                                .unwrap();
-                        }
-                        Err(e) => eprintln!("watch error: {:?}", e),
-                    }
-                })?;
-
-            app.manage(Watch(Mutex::new(w)));
+            // Raw notify events from every root's watcher land here,
+            // tagged with their root id, and get coalesced by the
+            // debounce thread before anything reaches the frontend.
+            let (tx, rx) = mpsc::channel::<(RootId, notify::Event)>();
+
+            let roots = Roots::new();
+            spawn_debounce_thread(rx, handle.clone(), roots.inner.clone());
+            app.manage(roots);
+            app.manage(EventSender(Mutex::new(tx)));
+
+            // Background indexer for bulk directory loads and refreshes
+            // that would otherwise block the UI thread.
+            let indexer_tx = spawn_indexer(handle.clone());
+            app.manage(Indexer(indexer_tx));
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             list_dir_files,
             open_file,
-            watch,
-            unwatch,
+            add_root,
+            remove_root,
             list_path_deep,
-            is_dir
+            is_dir,
+            index_dir,
+            refresh_file,
+            save_file
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod change_kind_tests {
+    use super::ChangeKind;
+
+    #[test]
+    fn first_event_on_a_path_passes_through() {
+        assert_eq!(ChangeKind::merge(None, ChangeKind::Create), Some(ChangeKind::Create));
+        assert_eq!(ChangeKind::merge(None, ChangeKind::Write), Some(ChangeKind::Write));
+        assert_eq!(ChangeKind::merge(None, ChangeKind::Remove), Some(ChangeKind::Remove));
+    }
+
+    #[test]
+    fn create_then_remove_within_the_window_cancels_out() {
+        // A file that's created and deleted before the debounce window
+        // closes never existed as far as the frontend is concerned.
+        assert_eq!(ChangeKind::merge(Some(ChangeKind::Create), ChangeKind::Remove), None);
+    }
+
+    #[test]
+    fn create_then_write_is_still_a_create() {
+        // The frontend only cares that the file is now there with this
+        // content, not that it was written to after being created.
+        assert_eq!(
+            ChangeKind::merge(Some(ChangeKind::Create), ChangeKind::Write),
+            Some(ChangeKind::Create)
+        );
+    }
+
+    #[test]
+    fn remove_then_create_is_a_write() {
+        // Some editors replace a file by deleting and recreating it; from
+        // the frontend's point of view that's an in-place edit.
+        assert_eq!(
+            ChangeKind::merge(Some(ChangeKind::Remove), ChangeKind::Create),
+            Some(ChangeKind::Write)
+        );
+    }
+
+    #[test]
+    fn anything_followed_by_remove_is_a_remove() {
+        assert_eq!(
+            ChangeKind::merge(Some(ChangeKind::Write), ChangeKind::Remove),
+            Some(ChangeKind::Remove)
+        );
+    }
+
+    #[test]
+    fn write_after_write_stays_a_write() {
+        assert_eq!(
+            ChangeKind::merge(Some(ChangeKind::Write), ChangeKind::Write),
+            Some(ChangeKind::Write)
+        );
+    }
+}
+
+#[cfg(test)]
+mod ignore_matcher_tests {
+    use super::is_ignored;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    // A scratch directory under the system temp dir, removed on drop so a
+    // panicking assertion doesn't leave litter behind.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new(name: &str) -> Self {
+            let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!(
+                "u-note-test-{name}-{}-{unique}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            ScratchDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn nested_ignore_file_is_picked_up_below_root() {
+        let scratch = ScratchDir::new("nested");
+        let root = scratch.path();
+        let sub = root.join("src");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(sub.join(".gitignore"), "*.log\n").unwrap();
+
+        assert!(is_ignored(root, &sub.join("debug.log"), false));
+        assert!(!is_ignored(root, &sub.join("main.rs"), false));
+    }
+
+    #[test]
+    fn negation_overrides_a_broader_pattern() {
+        let scratch = ScratchDir::new("negation");
+        let root = scratch.path();
+        fs::write(root.join(".gitignore"), "*.log\n!keep.log\n").unwrap();
+
+        assert!(is_ignored(root, &root.join("debug.log"), false));
+        assert!(!is_ignored(root, &root.join("keep.log"), false));
+    }
+
+    #[test]
+    fn trailing_slash_pattern_only_matches_directories() {
+        let scratch = ScratchDir::new("dir-only");
+        let root = scratch.path();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        assert!(is_ignored(root, &root.join("build"), true));
+        assert!(!is_ignored(root, &root.join("build"), false));
+    }
+
+    #[test]
+    fn directory_removal_is_still_matched_once_its_gone() {
+        // The watcher can't `stat` a path that's already been removed, so
+        // `is_ignored` has to take the caller's `is_dir` — derived from the
+        // notify event, not a fresh `path.is_dir()` — at face value.
+        let scratch = ScratchDir::new("removed-dir");
+        let root = scratch.path();
+        fs::write(root.join(".gitignore"), "node_modules/\n").unwrap();
+
+        assert!(is_ignored(root, &root.join("node_modules"), true));
+    }
+
+    #[test]
+    fn ancestor_ignore_files_above_root_are_respected() {
+        let scratch = ScratchDir::new("ancestor");
+        let parent = scratch.path();
+        fs::write(parent.join(".gitignore"), "*.secret\n").unwrap();
+        let root = parent.join("vault");
+        fs::create_dir_all(&root).unwrap();
+
+        assert!(is_ignored(&root, &root.join("keys.secret"), false));
+    }
+}